@@ -1,10 +1,10 @@
-use std::net::IpAddr;
+use std::{net::IpAddr, str::FromStr};
 
 use cidr_utils::{
-    cidr::{IpCidr, Ipv4Cidr},
+    cidr::{IpCidr, Ipv4Cidr, Ipv6Cidr},
     utils::IpCidrCombiner,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// URL for Cloudflare's canonical list of IP ranges.
 pub const CF_URL_IPS: &str = "https://api.cloudflare.com/client/v4/ips";
@@ -19,13 +19,13 @@ impl_more::impl_display_enum!(CfIpsFetchErr, Fetch => "failed to fetch");
 
 impl std::error::Error for CfIpsFetchErr {}
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CfIpsResult {
     ipv4_cidrs: Vec<cidr_utils::cidr::Ipv4Cidr>,
     ipv6_cidrs: Vec<cidr_utils::cidr::Ipv6Cidr>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum CfIpsResponse {
     Success { result: CfIpsResult },
@@ -80,6 +80,48 @@ impl TrustedIps {
         ))
     }
 
+    /// Adds the inclusive address range `start..=end` to this set, decomposed into the minimal
+    /// set of aligned CIDR blocks that cover it.
+    ///
+    /// `start` and `end` must be the same address family (both IPv4 or both IPv6); a mismatch is
+    /// logged and otherwise ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_client_ip_cloudflare::TrustedIps;
+    ///
+    /// let ips = TrustedIps::new().add_range(
+    ///     "10.0.0.5".parse().unwrap(),
+    ///     "10.0.0.40".parse().unwrap(),
+    /// );
+    /// assert!(ips.contains("10.0.0.20".parse().unwrap()));
+    /// assert!(!ips.contains("10.0.0.41".parse().unwrap()));
+    /// ```
+    pub fn add_range(mut self, start: IpAddr, end: IpAddr) -> Self {
+        for cidr in crate::range::range_to_cidrs(start, end) {
+            self.cidr_ranges.push(cidr);
+        }
+
+        self
+    }
+
+    /// Adds the ranges registered under `name` in `registry` to this set.
+    ///
+    /// Unknown alias names are logged and otherwise ignored.
+    pub fn add_alias(mut self, registry: &crate::range::TrustedIpAliasRegistry, name: &str) -> Self {
+        match registry.get(name) {
+            Some(ranges) => {
+                for &cidr in ranges {
+                    self.cidr_ranges.push(cidr);
+                }
+            }
+            None => tracing::warn!("unknown trusted IP alias: `{name}`"),
+        }
+
+        self
+    }
+
     /// Adds the `10.0.0.0/8` and `192.168.0.0/16` IP ranges to this set.
     pub fn add_private_ips(self) -> Self {
         self.add_ip_range(IpCidr::V4(
@@ -95,6 +137,26 @@ impl TrustedIps {
         self.cidr_ranges.contains(ip)
     }
 
+    /// Constructs a set of trusted IPs by fetching and merging the ranges of several
+    /// [`TrustedIpProvider`]s.
+    ///
+    /// This is useful when an origin server is fronted by more than one CDN or proxy (or is
+    /// migrating between them) and needs to trust all of their edge ranges at once.
+    pub async fn from_providers(
+        providers: impl IntoIterator<Item = Box<dyn crate::providers::TrustedIpProvider>>,
+    ) -> Result<Self, CfIpsFetchErr> {
+        let client = awc::Client::new();
+        let mut cidr_ranges = IpCidrCombiner::new();
+
+        for provider in providers {
+            for cidr in provider.fetch(&client).await? {
+                cidr_ranges.push(cidr);
+            }
+        }
+
+        Ok(Self { cidr_ranges })
+    }
+
     /// Constructs new set of trusted IPs from a deserialized Cloudflare response.
     pub fn try_from_response(res: CfIpsResponse) -> Result<Self, CfIpsFetchErr> {
         let ips = match res {
@@ -141,6 +203,157 @@ impl Clone for TrustedIps {
     }
 }
 
+/// Error parsing a [`TrustedIpRange`] or a [`TrustedIps`] config from a string.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TrustedIpsParseErr {
+    /// The given string was neither a valid CIDR range nor a valid IP address.
+    InvalidRange(String),
+}
+
+impl std::fmt::Display for TrustedIpsParseErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRange(range) => write!(f, "invalid trusted IP range: `{range}`"),
+        }
+    }
+}
+
+impl std::error::Error for TrustedIpsParseErr {}
+
+/// A single trusted IP range, parseable from a string.
+///
+/// Accepts CIDR notation (e.g. `10.0.0.0/8`) as well as plain IP addresses, which are treated as
+/// a `/32` (IPv4) or `/128` (IPv6) range.
+///
+/// ```
+/// use actix_client_ip_cloudflare::TrustedIpRange;
+///
+/// let range: TrustedIpRange = "203.0.113.0/24".parse().unwrap();
+/// let single_ip: TrustedIpRange = "203.0.113.5".parse().unwrap();
+/// assert!("not an ip".parse::<TrustedIpRange>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TrustedIpRange(pub IpCidr);
+
+impl FromStr for TrustedIpRange {
+    type Err = TrustedIpsParseErr;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let range = range.trim();
+
+        if let Ok(cidr) = IpCidr::from_str(range) {
+            return Ok(Self(cidr));
+        }
+
+        if let Ok(ip) = range.parse::<IpAddr>() {
+            let cidr = match ip {
+                IpAddr::V4(ip) => IpCidr::V4(Ipv4Cidr::from_prefix_and_bits(ip, 32).unwrap()),
+                IpAddr::V6(ip) => IpCidr::V6(Ipv6Cidr::from_prefix_and_bits(ip, 128).unwrap()),
+            };
+
+            return Ok(Self(cidr));
+        }
+
+        Err(TrustedIpsParseErr::InvalidRange(range.to_owned()))
+    }
+}
+
+/// Config shape accepted by [`TrustedIps`]'s [`Deserialize`] impl.
+///
+/// ```toml
+/// ranges = ["127.0.0.0/8", "203.0.113.5"]
+/// loopback = true
+/// private = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct TrustedIpsConfig {
+    #[serde(default)]
+    ranges: Vec<String>,
+
+    #[serde(default)]
+    loopback: bool,
+
+    #[serde(default)]
+    private: bool,
+}
+
+/// Parses a single range entry (CIDR, plain IP, or hyphenated `start-end`) and folds it into
+/// `trusted_ips`. Shared by [`TrustedIps`]'s [`Deserialize`] impl and its [`FromStr`] impl so the
+/// two accept exactly the same range syntax.
+fn parse_one_range(trusted_ips: TrustedIps, range: &str) -> Result<TrustedIps, TrustedIpsParseErr> {
+    match range.parse::<TrustedIpRange>() {
+        Ok(TrustedIpRange(cidr)) => Ok(trusted_ips.add_ip_range(cidr)),
+        Err(err) => match range.split_once('-') {
+            Some((start, end)) => {
+                let invalid = || TrustedIpsParseErr::InvalidRange(range.to_owned());
+                let start = start.trim().parse::<IpAddr>().map_err(|_| invalid())?;
+                let end = end.trim().parse::<IpAddr>().map_err(|_| invalid())?;
+
+                Ok(trusted_ips.add_range(start, end))
+            }
+            None => Err(err),
+        },
+    }
+}
+
+impl<'de> Deserialize<'de> for TrustedIps {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let config = TrustedIpsConfig::deserialize(deserializer)?;
+
+        let mut trusted_ips = TrustedIps::new();
+
+        if config.loopback {
+            trusted_ips = trusted_ips.add_loopback_ips();
+        }
+
+        if config.private {
+            trusted_ips = trusted_ips.add_private_ips();
+        }
+
+        for range in config.ranges {
+            trusted_ips = parse_one_range(trusted_ips, &range).map_err(serde::de::Error::custom)?;
+        }
+
+        Ok(trusted_ips)
+    }
+}
+
+/// Parses a comma-separated list of trusted IP ranges, e.g. the value of a `TRUSTED_IPS`
+/// environment variable.
+///
+/// Each entry accepts the same syntax as [`TrustedIpRange`] (CIDR notation or a plain IP) as well
+/// as a hyphenated `start-end` address range. Surrounding whitespace around entries is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use actix_client_ip_cloudflare::TrustedIps;
+///
+/// let ips: TrustedIps = "127.0.0.0/8, 10.0.0.0/8, 203.0.113.5"
+///     .parse()
+///     .unwrap();
+///
+/// assert!(ips.contains("127.0.0.1".parse().unwrap()));
+/// assert!(ips.contains("10.1.2.3".parse().unwrap()));
+/// assert!(ips.contains("203.0.113.5".parse().unwrap()));
+/// assert!(!ips.contains("8.8.8.8".parse().unwrap()));
+/// ```
+impl FromStr for TrustedIps {
+    type Err = TrustedIpsParseErr;
+
+    fn from_str(ranges: &str) -> Result<Self, Self::Err> {
+        ranges
+            .split(',')
+            .map(str::trim)
+            .filter(|range| !range.is_empty())
+            .try_fold(TrustedIps::new(), parse_one_range)
+    }
+}
+
 /// Fetched trusted Cloudflare IP addresses from their API.
 #[cfg(feature = "fetch-ips")]
 pub async fn fetch_trusted_cf_ips() -> Result<TrustedIps, CfIpsFetchErr> {
@@ -163,8 +376,6 @@ pub async fn fetch_trusted_cf_ips() -> Result<TrustedIps, CfIpsFetchErr> {
 
 #[cfg(test)]
 mod tests {
-    use cidr_utils::cidr::Ipv6Cidr;
-
     use super::*;
 
     #[test]
@@ -209,4 +420,95 @@ mod tests {
         assert!(ips.contains("127.0.0.1".parse().unwrap()));
         assert!(!ips.contains("10.0.1.1".parse().unwrap()));
     }
+
+    #[test]
+    fn trusted_ip_range_from_str() {
+        let TrustedIpRange(cidr) = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(cidr, IpCidr::V4(Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 0], 8).unwrap()));
+
+        let TrustedIpRange(cidr) = "203.0.113.5".parse().unwrap();
+        assert_eq!(
+            cidr,
+            IpCidr::V4(Ipv4Cidr::from_prefix_and_bits([203, 0, 113, 5], 32).unwrap())
+        );
+
+        assert!("not an ip".parse::<TrustedIpRange>().is_err());
+    }
+
+    #[test]
+    fn trusted_ips_deserialize() {
+        let ips: TrustedIps = toml::from_str(
+            r#"
+                ranges = ["203.0.113.0/24"]
+                loopback = true
+                private = true
+            "#,
+        )
+        .unwrap();
+
+        assert!(ips.contains("127.0.0.1".parse().unwrap()));
+        assert!(ips.contains("10.0.1.1".parse().unwrap()));
+        assert!(ips.contains("203.0.113.5".parse().unwrap()));
+        assert!(!ips.contains("8.8.8.8".parse().unwrap()));
+
+        assert!(toml::from_str::<TrustedIps>(r#"ranges = ["not an ip"]"#).is_err());
+    }
+
+    #[test]
+    fn trusted_ips_hyphenated_range() {
+        let ips: TrustedIps = toml::from_str(r#"ranges = ["10.0.0.5-10.0.0.40"]"#).unwrap();
+
+        assert!(ips.contains("10.0.0.5".parse().unwrap()));
+        assert!(ips.contains("10.0.0.20".parse().unwrap()));
+        assert!(ips.contains("10.0.0.40".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.4".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.41".parse().unwrap()));
+    }
+
+    #[test]
+    fn trusted_ips_add_range() {
+        let ips = TrustedIps::new().add_range(
+            "10.0.0.5".parse().unwrap(),
+            "10.0.0.40".parse().unwrap(),
+        );
+
+        assert!(ips.contains("10.0.0.5".parse().unwrap()));
+        assert!(ips.contains("10.0.0.20".parse().unwrap()));
+        assert!(ips.contains("10.0.0.40".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.4".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.41".parse().unwrap()));
+    }
+
+    #[test]
+    fn trusted_ips_from_str_comma_separated() {
+        let ips: TrustedIps = "127.0.0.0/8, 10.0.0.0/8, 203.0.113.5".parse().unwrap();
+
+        assert!(ips.contains("127.0.0.1".parse().unwrap()));
+        assert!(ips.contains("10.1.2.3".parse().unwrap()));
+        assert!(ips.contains("203.0.113.5".parse().unwrap()));
+        assert!(!ips.contains("8.8.8.8".parse().unwrap()));
+
+        let ips: TrustedIps = "10.0.0.5-10.0.0.40".parse().unwrap();
+        assert!(ips.contains("10.0.0.20".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.41".parse().unwrap()));
+
+        assert!("203.0.113.5, not an ip".parse::<TrustedIps>().is_err());
+    }
+
+    #[test]
+    fn trusted_ips_add_alias() {
+        let registry = crate::range::TrustedIpAliasRegistry::new().register(
+            "office-net",
+            [IpCidr::V4(
+                Ipv4Cidr::from_prefix_and_bits([192, 168, 1, 0], 24).unwrap(),
+            )],
+        );
+
+        let ips = TrustedIps::new().add_alias(&registry, "office-net");
+        assert!(ips.contains("192.168.1.42".parse().unwrap()));
+        assert!(!ips.contains("192.168.2.1".parse().unwrap()));
+
+        let ips = TrustedIps::new().add_alias(&registry, "unknown-alias");
+        assert!(!ips.contains("192.168.1.42".parse().unwrap()));
+    }
 }
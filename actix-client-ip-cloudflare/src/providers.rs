@@ -0,0 +1,333 @@
+use cidr_utils::cidr::{IpCidr, Ipv4Cidr, Ipv6Cidr};
+use serde::Deserialize;
+
+use crate::CfIpsFetchErr;
+
+/// A source of trusted edge/proxy IP ranges.
+///
+/// Implement this trait to teach [`TrustedIps`](crate::TrustedIps) how to fetch and parse the
+/// published IP ranges of a CDN or reverse-proxy provider other than Cloudflare. See
+/// [`CloudflareProvider`], [`CloudfrontProvider`], [`FastlyProvider`], and
+/// [`GoogleCloudProvider`] for the bundled implementations.
+#[async_trait::async_trait]
+pub trait TrustedIpProvider {
+    /// Fetches and parses this provider's published IP ranges.
+    async fn fetch(&self, client: &awc::Client) -> Result<Vec<IpCidr>, CfIpsFetchErr>;
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(
+    client: &awc::Client,
+    url: &str,
+) -> Result<T, CfIpsFetchErr> {
+    tracing::debug!("fetching IP ranges from {url}");
+
+    let mut res = client.get(url).send().await.map_err(|err| {
+        tracing::error!("{err}");
+        CfIpsFetchErr::Fetch
+    })?;
+
+    res.json::<T>().await.map_err(|err| {
+        tracing::error!("{err}");
+        CfIpsFetchErr::Fetch
+    })
+}
+
+/// Fetches trusted IP ranges from [Cloudflare's API](crate::CF_URL_IPS).
+///
+/// Queries the same endpoint and parses the same response shape as
+/// [`fetch_trusted_cf_ips()`](crate::fetch_trusted_cf_ips); that function does not go through
+/// this provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloudflareProvider;
+
+fn parse_cloudflare(res: crate::CfIpsResponse) -> Result<Vec<IpCidr>, CfIpsFetchErr> {
+    let trusted_ips = crate::TrustedIps::try_from_response(res)?;
+
+    Ok(trusted_ips
+        .cidr_ranges
+        .get_ipv4_cidrs()
+        .iter()
+        .copied()
+        .map(IpCidr::V4)
+        .chain(
+            trusted_ips
+                .cidr_ranges
+                .get_ipv6_cidrs()
+                .iter()
+                .copied()
+                .map(IpCidr::V6),
+        )
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl TrustedIpProvider for CloudflareProvider {
+    async fn fetch(&self, client: &awc::Client) -> Result<Vec<IpCidr>, CfIpsFetchErr> {
+        let res = fetch_json::<crate::CfIpsResponse>(client, crate::CF_URL_IPS).await?;
+        parse_cloudflare(res)
+    }
+}
+
+/// URL for AWS CloudFront's published IP ranges.
+///
+/// This list is shared by all AWS services; CloudFront ranges are filtered out by `service`.
+pub const CLOUDFRONT_URL_IPS: &str = "https://ip-ranges.amazonaws.com/ip-ranges.json";
+
+#[derive(Debug, Deserialize)]
+struct CloudfrontIpRangesResponse {
+    prefixes: Vec<CloudfrontIpv4Prefix>,
+    ipv6_prefixes: Vec<CloudfrontIpv6Prefix>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloudfrontIpv4Prefix {
+    ip_prefix: String,
+    service: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloudfrontIpv6Prefix {
+    ipv6_prefix: String,
+    service: String,
+}
+
+/// Fetches trusted IP ranges from [AWS CloudFront's `ip-ranges.json`](CLOUDFRONT_URL_IPS).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloudfrontProvider;
+
+fn parse_cloudfront(res: CloudfrontIpRangesResponse) -> Vec<IpCidr> {
+    let mut cidrs = Vec::new();
+
+    for prefix in res.prefixes {
+        if prefix.service != "CLOUDFRONT" {
+            continue;
+        }
+
+        match prefix.ip_prefix.parse::<Ipv4Cidr>() {
+            Ok(cidr) => cidrs.push(IpCidr::V4(cidr)),
+            Err(err) => tracing::error!("invalid CloudFront IPv4 prefix: {err}"),
+        }
+    }
+
+    for prefix in res.ipv6_prefixes {
+        if prefix.service != "CLOUDFRONT" {
+            continue;
+        }
+
+        match prefix.ipv6_prefix.parse::<Ipv6Cidr>() {
+            Ok(cidr) => cidrs.push(IpCidr::V6(cidr)),
+            Err(err) => tracing::error!("invalid CloudFront IPv6 prefix: {err}"),
+        }
+    }
+
+    cidrs
+}
+
+#[async_trait::async_trait]
+impl TrustedIpProvider for CloudfrontProvider {
+    async fn fetch(&self, client: &awc::Client) -> Result<Vec<IpCidr>, CfIpsFetchErr> {
+        let res = fetch_json::<CloudfrontIpRangesResponse>(client, CLOUDFRONT_URL_IPS).await?;
+        Ok(parse_cloudfront(res))
+    }
+}
+
+/// URL for Fastly's published IP ranges.
+pub const FASTLY_URL_IPS: &str = "https://api.fastly.com/public-ip-list";
+
+#[derive(Debug, Deserialize)]
+struct FastlyIpRangesResponse {
+    addresses: Vec<String>,
+    ipv6_addresses: Vec<String>,
+}
+
+/// Fetches trusted IP ranges from [Fastly's `/public-ip-list`](FASTLY_URL_IPS).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FastlyProvider;
+
+fn parse_fastly(res: FastlyIpRangesResponse) -> Vec<IpCidr> {
+    res.addresses
+        .iter()
+        .filter_map(|cidr| match cidr.parse::<Ipv4Cidr>() {
+            Ok(cidr) => Some(IpCidr::V4(cidr)),
+            Err(err) => {
+                tracing::error!("invalid Fastly IPv4 range: {err}");
+                None
+            }
+        })
+        .chain(
+            res.ipv6_addresses
+                .iter()
+                .filter_map(|cidr| match cidr.parse::<Ipv6Cidr>() {
+                    Ok(cidr) => Some(IpCidr::V6(cidr)),
+                    Err(err) => {
+                        tracing::error!("invalid Fastly IPv6 range: {err}");
+                        None
+                    }
+                }),
+        )
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl TrustedIpProvider for FastlyProvider {
+    async fn fetch(&self, client: &awc::Client) -> Result<Vec<IpCidr>, CfIpsFetchErr> {
+        let res = fetch_json::<FastlyIpRangesResponse>(client, FASTLY_URL_IPS).await?;
+        Ok(parse_fastly(res))
+    }
+}
+
+/// URL for Google Cloud's published IP ranges.
+///
+/// This list is not limited to load balancers or CDN edges — it covers all of Google Cloud's
+/// external ranges, including, e.g., plain Compute Engine egress. Trusting it means trusting
+/// every GCP customer's outbound traffic, not just traffic routed through a Google-managed
+/// load balancer.
+pub const GOOGLE_CLOUD_URL_IPS: &str = "https://www.gstatic.com/ipranges/cloud.json";
+
+#[derive(Debug, Deserialize)]
+struct GoogleCloudIpRangesResponse {
+    prefixes: Vec<GoogleCloudPrefix>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleCloudPrefix {
+    #[serde(default, rename = "ipv4Prefix")]
+    ipv4_prefix: Option<String>,
+    #[serde(default, rename = "ipv6Prefix")]
+    ipv6_prefix: Option<String>,
+}
+
+/// Fetches trusted IP ranges from [Google Cloud's published ranges](GOOGLE_CLOUD_URL_IPS).
+///
+/// Unlike [`CloudfrontProvider`], this returns every external GCP range unfiltered — Google's
+/// list has no equivalent per-prefix `service` field to scope it down to load balancers or CDN
+/// edges, so use this only when your origin truly expects traffic from anywhere in Google Cloud.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GoogleCloudProvider;
+
+fn parse_google_cloud(res: GoogleCloudIpRangesResponse) -> Vec<IpCidr> {
+    let mut cidrs = Vec::new();
+
+    for prefix in res.prefixes {
+        if let Some(ipv4) = prefix.ipv4_prefix {
+            match ipv4.parse::<Ipv4Cidr>() {
+                Ok(cidr) => cidrs.push(IpCidr::V4(cidr)),
+                Err(err) => tracing::error!("invalid Google Cloud IPv4 prefix: {err}"),
+            }
+        }
+
+        if let Some(ipv6) = prefix.ipv6_prefix {
+            match ipv6.parse::<Ipv6Cidr>() {
+                Ok(cidr) => cidrs.push(IpCidr::V6(cidr)),
+                Err(err) => tracing::error!("invalid Google Cloud IPv6 prefix: {err}"),
+            }
+        }
+    }
+
+    cidrs
+}
+
+#[async_trait::async_trait]
+impl TrustedIpProvider for GoogleCloudProvider {
+    async fn fetch(&self, client: &awc::Client) -> Result<Vec<IpCidr>, CfIpsFetchErr> {
+        let res = fetch_json::<GoogleCloudIpRangesResponse>(client, GOOGLE_CLOUD_URL_IPS).await?;
+        Ok(parse_google_cloud(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cloudflare_fixture() {
+        let res: crate::CfIpsResponse = serde_json::from_str(
+            r#"{
+                "result": {
+                    "ipv4_cidrs": ["173.245.48.0/20"],
+                    "ipv6_cidrs": ["2400:cb00::/32"]
+                },
+                "success": true
+            }"#,
+        )
+        .unwrap();
+
+        let cidrs = parse_cloudflare(res).unwrap();
+        assert_eq!(
+            cidrs,
+            vec![
+                IpCidr::V4("173.245.48.0/20".parse().unwrap()),
+                IpCidr::V6("2400:cb00::/32".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_cloudfront_fixture_and_filters_by_service() {
+        let res: CloudfrontIpRangesResponse = serde_json::from_str(
+            r#"{
+                "prefixes": [
+                    { "ip_prefix": "13.32.0.0/15", "service": "CLOUDFRONT" },
+                    { "ip_prefix": "3.2.34.0/26", "service": "EC2" }
+                ],
+                "ipv6_prefixes": [
+                    { "ipv6_prefix": "2600:9000::/28", "service": "CLOUDFRONT" },
+                    { "ipv6_prefix": "2a05:d000::/25", "service": "ROUTE53_HEALTHCHECKS" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let cidrs = parse_cloudfront(res);
+        assert_eq!(
+            cidrs,
+            vec![
+                IpCidr::V4("13.32.0.0/15".parse().unwrap()),
+                IpCidr::V6("2600:9000::/28".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_fastly_fixture() {
+        let res: FastlyIpRangesResponse = serde_json::from_str(
+            r#"{
+                "addresses": ["23.235.32.0/20"],
+                "ipv6_addresses": ["2a04:4e40::/32"]
+            }"#,
+        )
+        .unwrap();
+
+        let cidrs = parse_fastly(res);
+        assert_eq!(
+            cidrs,
+            vec![
+                IpCidr::V4("23.235.32.0/20".parse().unwrap()),
+                IpCidr::V6("2a04:4e40::/32".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_google_cloud_fixture() {
+        let res: GoogleCloudIpRangesResponse = serde_json::from_str(
+            r#"{
+                "prefixes": [
+                    { "ipv4Prefix": "34.80.0.0/15" },
+                    { "ipv6Prefix": "2600:1900::/28" },
+                    { "service": "Google Cloud" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let cidrs = parse_google_cloud(res);
+        assert_eq!(
+            cidrs,
+            vec![
+                IpCidr::V4("34.80.0.0/15".parse().unwrap()),
+                IpCidr::V6("2600:1900::/28".parse().unwrap()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use cidr_utils::cidr::{IpCidr, Ipv4Cidr, Ipv6Cidr};
+
+/// Splits the inclusive range `[start, end]` into the minimal set of aligned CIDR blocks.
+///
+/// Repeatedly emits the largest prefix whose base address equals the current `start` and whose
+/// block does not overrun `end`, then advances `start` past it.
+pub(crate) fn ipv4_range_to_cidrs(mut start: u32, end: u32) -> Vec<Ipv4Cidr> {
+    let mut cidrs = Vec::new();
+
+    if start > end {
+        return cidrs;
+    }
+
+    loop {
+        // the largest block alignable at `start`, based on its trailing zero bits
+        let max_prefix_len = 32 - start.trailing_zeros().min(32);
+
+        // shrink the block until it no longer overruns `end`
+        let prefix_len = (max_prefix_len..=32)
+            .find(|&prefix_len| {
+                let block_size = 1u64 << (32 - prefix_len);
+                start as u64 + block_size - 1 <= end as u64
+            })
+            .unwrap_or(32);
+
+        cidrs.push(Ipv4Cidr::from_prefix_and_bits(Ipv4Addr::from(start), prefix_len as u8).unwrap());
+
+        let block_size = 1u64 << (32 - prefix_len);
+        let next_start = start as u64 + block_size;
+
+        if next_start > end as u64 {
+            break;
+        }
+
+        start = next_start as u32;
+    }
+
+    cidrs
+}
+
+/// Splits the inclusive range `[start, end]` into the minimal set of aligned CIDR blocks.
+///
+/// See [`ipv4_range_to_cidrs`] for the algorithm; this is the same thing over the IPv6 address
+/// space.
+pub(crate) fn ipv6_range_to_cidrs(mut start: u128, end: u128) -> Vec<Ipv6Cidr> {
+    let mut cidrs = Vec::new();
+
+    if start > end {
+        return cidrs;
+    }
+
+    loop {
+        // the largest block alignable at `start`, based on its trailing zero bits
+        let max_prefix_len = 128 - start.trailing_zeros().min(128);
+
+        // shrink the block until it no longer overruns `end`. `prefix_len == 0` covers the
+        // entire address space and is special-cased: `1u128 << 128` would overflow, and the
+        // block is only valid in that case if it doesn't overrun `end` either, i.e. `end` is
+        // `u128::MAX`.
+        let prefix_len = (max_prefix_len..=128)
+            .find(|&prefix_len| {
+                if prefix_len == 0 {
+                    return end == u128::MAX;
+                }
+
+                let block_size = 1u128 << (128 - prefix_len);
+                start.checked_add(block_size - 1).is_some_and(|last| last <= end)
+            })
+            .unwrap_or(128);
+
+        cidrs.push(Ipv6Cidr::from_prefix_and_bits(Ipv6Addr::from(start), prefix_len as u8).unwrap());
+
+        if prefix_len == 0 {
+            // the block just pushed already covers up to `u128::MAX`; nothing left to advance to
+            break;
+        }
+
+        let block_size = 1u128 << (128 - prefix_len);
+
+        let Some(next_start) = start.checked_add(block_size) else {
+            break;
+        };
+
+        if next_start > end {
+            break;
+        }
+
+        start = next_start;
+    }
+
+    cidrs
+}
+
+/// Decomposes an inclusive, same-family IP address range into the minimal set of aligned CIDR
+/// blocks covering it.
+pub(crate) fn range_to_cidrs(start: IpAddr, end: IpAddr) -> Vec<IpCidr> {
+    match (start, end) {
+        (IpAddr::V4(start), IpAddr::V4(end)) => ipv4_range_to_cidrs(start.into(), end.into())
+            .into_iter()
+            .map(IpCidr::V4)
+            .collect(),
+        (IpAddr::V6(start), IpAddr::V6(end)) => ipv6_range_to_cidrs(start.into(), end.into())
+            .into_iter()
+            .map(IpCidr::V6)
+            .collect(),
+        _ => {
+            tracing::warn!("trusted IP range start and end must be the same address family");
+            Vec::new()
+        }
+    }
+}
+
+/// A registry of named groups of trusted IP ranges, so a group can be referred to by a single
+/// alias (e.g. `office-net`) the way firewall configs do, instead of repeating its ranges.
+///
+/// Register aliases up front, then use [`TrustedIps::add_alias()`](crate::TrustedIps::add_alias)
+/// to pull a named group into a set being built. Aliases are resolved only through that method;
+/// the `Deserialize` and `FromStr` impls of `TrustedIps` do not recognise alias names.
+#[derive(Debug, Default, Clone)]
+pub struct TrustedIpAliasRegistry {
+    aliases: HashMap<String, Vec<IpCidr>>,
+}
+
+impl TrustedIpAliasRegistry {
+    /// Constructs a new, empty alias registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a named group of IP ranges.
+    pub fn register(mut self, name: impl Into<String>, ranges: impl IntoIterator<Item = IpCidr>) -> Self {
+        self.aliases.insert(name.into(), ranges.into_iter().collect());
+        self
+    }
+
+    /// Returns the ranges registered under `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&[IpCidr]> {
+        self.aliases.get(name).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_range_splitting() {
+        // single aligned /24 block
+        let cidrs = ipv4_range_to_cidrs(
+            u32::from(Ipv4Addr::new(10, 0, 0, 0)),
+            u32::from(Ipv4Addr::new(10, 0, 0, 255)),
+        );
+        assert_eq!(cidrs, vec![Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 0], 24).unwrap()]);
+
+        // unaligned range needs multiple blocks
+        let cidrs = ipv4_range_to_cidrs(
+            u32::from(Ipv4Addr::new(10, 0, 0, 5)),
+            u32::from(Ipv4Addr::new(10, 0, 0, 40)),
+        );
+        assert_eq!(
+            cidrs,
+            vec![
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 5], 32).unwrap(),
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 6], 31).unwrap(),
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 8], 29).unwrap(),
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 16], 28).unwrap(),
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 32], 29).unwrap(),
+                Ipv4Cidr::from_prefix_and_bits([10, 0, 0, 40], 32).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn range_to_cidrs_rejects_mismatched_families() {
+        let start = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0));
+        let end = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert!(range_to_cidrs(start, end).is_empty());
+    }
+
+    #[test]
+    fn ipv6_range_starting_at_unspecified_does_not_panic() {
+        let cidrs = ipv6_range_to_cidrs(0, u128::from(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0xff)));
+        assert_eq!(
+            cidrs,
+            vec![Ipv6Cidr::from_prefix_and_bits(Ipv6Addr::UNSPECIFIED, 120).unwrap()]
+        );
+    }
+
+    #[test]
+    fn ipv6_range_reaching_top_of_address_space_does_not_panic() {
+        let start = u128::MAX - 1;
+        let cidrs = ipv6_range_to_cidrs(start, u128::MAX);
+        assert_eq!(cidrs, vec![Ipv6Cidr::from_prefix_and_bits(Ipv6Addr::from(start), 127).unwrap()]);
+    }
+
+    #[test]
+    fn ipv6_full_address_space_does_not_panic() {
+        let cidrs = ipv6_range_to_cidrs(0, u128::MAX);
+        assert_eq!(cidrs, vec![Ipv6Cidr::from_prefix_and_bits(Ipv6Addr::UNSPECIFIED, 0).unwrap()]);
+    }
+}
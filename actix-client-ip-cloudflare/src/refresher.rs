@@ -0,0 +1,193 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use arc_swap::ArcSwap;
+use rand::Rng as _;
+use tokio::task::JoinHandle;
+
+use crate::{fetch_trusted_cf_ips, TrustedIps};
+
+/// What to do when a background refresh of [`TrustedIps`] fails.
+///
+/// Either way, the last successfully fetched set of trusted IPs keeps being served; this only
+/// controls how the failure is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RefreshFailurePolicy {
+    /// Log the failure at `warn` level and move on.
+    KeepLastGood,
+
+    /// Log the failure at `error` level and mark the refresher unhealthy, per
+    /// [`TrustedIpsRefresher::is_healthy()`], until a subsequent refresh succeeds. Useful when a
+    /// stale trusted-IP set for more than one interval should page someone.
+    Error,
+}
+
+/// Builder for [`TrustedIpsRefresher`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use actix_client_ip_cloudflare::TrustedIpsRefresherBuilder;
+/// # async fn example() {
+/// let refresher = TrustedIpsRefresherBuilder::new()
+///     .interval(Duration::from_secs(60 * 60 * 12))
+///     .jitter(Duration::from_secs(60))
+///     .failure_policy(actix_client_ip_cloudflare::RefreshFailurePolicy::KeepLastGood)
+///     .spawn()
+///     .await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrustedIpsRefresherBuilder {
+    interval: Duration,
+    jitter: Option<Duration>,
+    failure_policy: RefreshFailurePolicy,
+}
+
+impl Default for TrustedIpsRefresherBuilder {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60 * 60 * 12),
+            jitter: None,
+            failure_policy: RefreshFailurePolicy::KeepLastGood,
+        }
+    }
+}
+
+impl TrustedIpsRefresherBuilder {
+    /// Constructs new refresher builder with a default 12-hour polling interval.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interval between polls of [`CF_URL_IPS`](crate::CF_URL_IPS).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets a random jitter, up to the given duration, that is added to each interval.
+    ///
+    /// Useful for avoiding a thundering herd of fleet-wide refreshes all hitting Cloudflare's API
+    /// at the exact same moment.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Sets what happens when a poll fails. Defaults to [`RefreshFailurePolicy::KeepLastGood`].
+    pub fn failure_policy(mut self, failure_policy: RefreshFailurePolicy) -> Self {
+        self.failure_policy = failure_policy;
+        self
+    }
+
+    /// Performs an initial fetch and spawns the background refresh task.
+    ///
+    /// The returned guard owns the background task; dropping it cancels the task.
+    pub async fn spawn(self) -> Result<TrustedIpsRefresher, crate::CfIpsFetchErr> {
+        let initial = fetch_trusted_cf_ips().await?;
+        Ok(self.spawn_with_initial(initial))
+    }
+
+    /// Spawns the background refresh task, seeding it with an already-known set of trusted IPs
+    /// instead of performing an initial fetch.
+    pub fn spawn_with_initial(self, initial: TrustedIps) -> TrustedIpsRefresher {
+        let shared = Arc::new(ArcSwap::from_pointee(initial));
+        let healthy = Arc::new(AtomicBool::new(true));
+
+        let task_shared = Arc::clone(&shared);
+        let task_healthy = Arc::clone(&healthy);
+        let interval = self.interval;
+        let jitter = self.jitter;
+        let failure_policy = self.failure_policy;
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let sleep_for = match jitter {
+                    Some(jitter) if !jitter.is_zero() => {
+                        interval + rand::thread_rng().gen_range(Duration::ZERO..jitter)
+                    }
+                    _ => interval,
+                };
+
+                tokio::time::sleep(sleep_for).await;
+
+                tracing::debug!("refreshing trusted cloudflare ips");
+
+                match fetch_trusted_cf_ips().await {
+                    Ok(fresh) => {
+                        tracing::info!("refreshed trusted cloudflare ips");
+                        task_shared.store(Arc::new(fresh));
+                        task_healthy.store(true, Ordering::Relaxed);
+                    }
+                    Err(err) => match failure_policy {
+                        RefreshFailurePolicy::KeepLastGood => {
+                            tracing::warn!("failed to refresh trusted cloudflare ips, keeping last-good set: {err}");
+                        }
+                        RefreshFailurePolicy::Error => {
+                            tracing::error!("failed to refresh trusted cloudflare ips, keeping last-good set: {err}");
+                            task_healthy.store(false, Ordering::Relaxed);
+                        }
+                    },
+                }
+            }
+        });
+
+        TrustedIpsRefresher {
+            shared,
+            healthy,
+            handle,
+        }
+    }
+}
+
+/// Keeps a [`TrustedIps`] set up to date by periodically polling Cloudflare's API in the
+/// background.
+///
+/// Construct one using [`TrustedIpsRefresherBuilder`]. The current set of trusted IPs is
+/// accessible, lock-free, via [`current()`](Self::current), which is what
+/// [`TrustedClientIp`](crate::TrustedClientIp) should be configured to read from when using this
+/// subsystem. Dropping the refresher cancels its background task.
+#[derive(Debug)]
+pub struct TrustedIpsRefresher {
+    shared: Arc<ArcSwap<TrustedIps>>,
+    healthy: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl TrustedIpsRefresher {
+    /// Starts building a new refresher.
+    pub fn builder() -> TrustedIpsRefresherBuilder {
+        TrustedIpsRefresherBuilder::new()
+    }
+
+    /// Returns the current set of trusted IPs.
+    ///
+    /// This is a lock-free read; it is cheap enough to call on every request.
+    pub fn current(&self) -> Arc<TrustedIps> {
+        self.shared.load_full()
+    }
+
+    /// Returns `false` if the most recent refresh failed under
+    /// [`RefreshFailurePolicy::Error`], and no subsequent refresh has succeeded since. Always
+    /// `true` under [`RefreshFailurePolicy::KeepLastGood`].
+    ///
+    /// [`current()`](Self::current) keeps serving the last-good set regardless; this is purely a
+    /// signal for callers (e.g. a liveness probe) that want to know the set may be stale.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for TrustedIpsRefresher {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
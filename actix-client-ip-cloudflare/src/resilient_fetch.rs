@@ -0,0 +1,281 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CfIpsFetchErr, CfIpsResponse, TrustedIps, CF_URL_IPS};
+
+/// Cloudflare's published IP ranges, compiled into the crate as a last-resort fallback for when
+/// the API is unreachable and no on-disk cache exists.
+///
+/// This is a point-in-time snapshot; prefer [`fetch_trusted_cf_ips_resilient()`] with a cache
+/// path so a live fetch overrides it as soon as one succeeds.
+#[cfg(feature = "fallback-snapshot")]
+pub const FALLBACK_CF_IPS_SNAPSHOT: &str = include_str!("../snapshots/cloudflare_ips.json");
+
+/// Options controlling [`fetch_trusted_cf_ips_resilient()`]'s retry, caching, and fallback
+/// behaviour.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// use actix_client_ip_cloudflare::FetchOptions;
+///
+/// let opts = FetchOptions::new()
+///     .max_retries(3)
+///     .backoff(Duration::from_millis(250), Duration::from_secs(30))
+///     .request_timeout(Duration::from_secs(10))
+///     .total_timeout(Duration::from_secs(30))
+///     .cache_path("/var/cache/myapp/cf-ips.json");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    max_retries: u32,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    request_timeout: Duration,
+    total_timeout: Duration,
+    cache_path: Option<PathBuf>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(10),
+            total_timeout: Duration::from_secs(30),
+            cache_path: None,
+        }
+    }
+}
+
+impl FetchOptions {
+    /// Constructs new fetch options with sensible defaults: 3 retries, exponential backoff from
+    /// 250ms up to 10s, a 10s per-attempt timeout, a 30s overall deadline across all attempts and
+    /// backoff sleeps, and no on-disk cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of retries attempted after the first failed request.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the exponential backoff range between retries.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_backoff = base;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets the timeout applied to each individual request attempt.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Sets the overall deadline across all attempts and backoff sleeps combined.
+    ///
+    /// Once this elapses, [`fetch_trusted_cf_ips_resilient()`] stops retrying and falls back to
+    /// the on-disk cache or bundled snapshot, same as if `max_retries` had been exhausted.
+    pub fn total_timeout(mut self, total_timeout: Duration) -> Self {
+        self.total_timeout = total_timeout;
+        self
+    }
+
+    /// Sets a path used to persist the last successful response, so a restart during an API
+    /// outage can still boot with the previous good set of ranges.
+    pub fn cache_path(mut self, cache_path: impl Into<PathBuf>) -> Self {
+        self.cache_path = Some(cache_path.into());
+        self
+    }
+}
+
+/// On-disk representation of a cached fetch, including the validators needed to make a
+/// conditional request next time.
+#[derive(Debug, Serialize, Deserialize)]
+struct FetchCache {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    response: CfIpsResponse,
+}
+
+fn read_cache(cache_path: &Path) -> Option<FetchCache> {
+    let contents = std::fs::read_to_string(cache_path)
+        .map_err(|err| tracing::debug!("no usable trusted IP cache at {cache_path:?}: {err}"))
+        .ok()?;
+
+    serde_json::from_str(&contents)
+        .map_err(|err| tracing::warn!("trusted IP cache at {cache_path:?} is corrupt: {err}"))
+        .ok()
+}
+
+fn write_cache(cache_path: &Path, cache: &FetchCache) {
+    let Ok(serialized) = serde_json::to_string(cache) else {
+        tracing::warn!("failed to serialize trusted IP cache");
+        return;
+    };
+
+    if let Err(err) = std::fs::write(cache_path, serialized) {
+        tracing::warn!("failed to write trusted IP cache at {cache_path:?}: {err}");
+    }
+}
+
+/// Performs a single fetch attempt, sending `If-None-Match`/`If-Modified-Since` when validators
+/// from a previous successful fetch are available.
+async fn fetch_once(
+    client: &awc::Client,
+    timeout: Duration,
+    prev: Option<&FetchCache>,
+) -> Result<FetchOutcome, CfIpsFetchErr> {
+    let mut req = client.get(CF_URL_IPS).timeout(timeout);
+
+    if let Some(prev) = prev {
+        if let Some(etag) = &prev.etag {
+            req = req.insert_header(("If-None-Match", etag.as_str()));
+        }
+
+        if let Some(last_modified) = &prev.last_modified {
+            req = req.insert_header(("If-Modified-Since", last_modified.as_str()));
+        }
+    }
+
+    let mut res = req.send().await.map_err(|err| {
+        tracing::error!("{err}");
+        CfIpsFetchErr::Fetch
+    })?;
+
+    if res.status() == awc::http::StatusCode::NOT_MODIFIED {
+        return Ok(FetchOutcome::NotModified);
+    }
+
+    let etag = res
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+
+    let last_modified = res
+        .headers()
+        .get("last-modified")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+
+    let response = res.json::<CfIpsResponse>().await.map_err(|err| {
+        tracing::error!("{err}");
+        CfIpsFetchErr::Fetch
+    })?;
+
+    if let CfIpsResponse::Failure { .. } = response {
+        tracing::error!("parsing response returned success: false");
+        return Err(CfIpsFetchErr::Fetch);
+    }
+
+    Ok(FetchOutcome::Fresh(FetchCache {
+        etag,
+        last_modified,
+        response,
+    }))
+}
+
+enum FetchOutcome {
+    Fresh(FetchCache),
+    NotModified,
+}
+
+/// Retries [`fetch_once()`] with exponential backoff, up to `opts.max_retries` times, with no cap
+/// on its own wall-time; the caller is expected to race this against `opts.total_timeout`.
+async fn fetch_with_retries(
+    client: &awc::Client,
+    opts: &FetchOptions,
+    prev: Option<&FetchCache>,
+) -> Result<TrustedIps, CfIpsFetchErr> {
+    let mut attempt = 0;
+    let mut backoff = opts.base_backoff;
+
+    loop {
+        match fetch_once(client, opts.request_timeout, prev).await {
+            Ok(FetchOutcome::NotModified) => {
+                tracing::debug!("trusted cloudflare ips not modified");
+
+                // a `304` is only ever sent in response to conditional headers we ourselves
+                // attach when `prev` is `Some`; treat an unsolicited one as a protocol violation
+                // rather than panicking.
+                let Some(prev) = prev else {
+                    tracing::error!("server sent 304 Not Modified without a conditional request");
+                    return Err(CfIpsFetchErr::Fetch);
+                };
+
+                return TrustedIps::try_from_response(prev.response.clone());
+            }
+            Ok(FetchOutcome::Fresh(cache)) => {
+                tracing::debug!("fetched fresh trusted cloudflare ips");
+
+                let trusted_ips = TrustedIps::try_from_response(cache.response.clone())?;
+
+                if let Some(cache_path) = &opts.cache_path {
+                    write_cache(cache_path, &cache);
+                }
+
+                return Ok(trusted_ips);
+            }
+            Err(err) if attempt < opts.max_retries => {
+                tracing::warn!("fetch attempt {attempt} failed, retrying in {backoff:?}: {err}");
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+                backoff = (backoff * 2).min(opts.max_backoff);
+            }
+            Err(err) => {
+                tracing::error!("all {attempt} retries exhausted: {err}");
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Fetches trusted Cloudflare IP addresses from their API, resiliently.
+///
+/// On failure, retries with exponential backoff up to `opts.max_retries` times, bounded overall
+/// by `opts.total_timeout`. Sends conditional-request headers when a previous response was
+/// cached, treating `304 Not Modified` as "the cached set is still current". If every attempt
+/// fails, or the overall deadline elapses first, falls back to the on-disk cache at
+/// `opts.cache_path` (if configured and present), and finally to the
+/// [`FALLBACK_CF_IPS_SNAPSHOT`] compiled into the crate, if the `fallback-snapshot` feature is
+/// enabled. Returns an error only if all of these are exhausted.
+#[cfg(feature = "fetch-ips")]
+pub async fn fetch_trusted_cf_ips_resilient(opts: &FetchOptions) -> Result<TrustedIps, CfIpsFetchErr> {
+    let client = awc::Client::new();
+
+    let prev = opts.cache_path.as_ref().and_then(read_cache);
+
+    match tokio::time::timeout(opts.total_timeout, fetch_with_retries(&client, opts, prev.as_ref())).await {
+        Ok(Ok(trusted_ips)) => return Ok(trusted_ips),
+        Ok(Err(err)) => tracing::error!("fetch failed: {err}"),
+        Err(_) => tracing::error!("overall fetch deadline of {:?} elapsed", opts.total_timeout),
+    }
+
+    if let Some(prev) = prev {
+        tracing::warn!("falling back to last cached trusted cloudflare ips");
+        return TrustedIps::try_from_response(prev.response);
+    }
+
+    #[cfg(feature = "fallback-snapshot")]
+    {
+        tracing::warn!("falling back to the trusted cloudflare ip snapshot bundled in this crate");
+        let response = serde_json::from_str(FALLBACK_CF_IPS_SNAPSHOT).map_err(|err| {
+            tracing::error!("bundled fallback snapshot is corrupt: {err}");
+            CfIpsFetchErr::Fetch
+        })?;
+        return TrustedIps::try_from_response(response);
+    }
+
+    #[cfg(not(feature = "fallback-snapshot"))]
+    Err(CfIpsFetchErr::Fetch)
+}
@@ -0,0 +1,303 @@
+use std::net::IpAddr;
+
+use crate::TrustedIps;
+
+/// Error resolving the real client IP from a proxy chain.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ClientIpResolveErr {
+    /// Every hop in the `X-Forwarded-For` (or `Forwarded`) chain, including the connection peer,
+    /// was trusted, so there was no untrusted address left to treat as the real client IP.
+    ChainFullyTrusted,
+
+    /// The header was present but could not be parsed.
+    Header,
+}
+
+impl_more::impl_display_enum!(
+    ClientIpResolveErr,
+    ChainFullyTrusted => "every hop in the proxy chain is trusted; no client IP to extract",
+    Header => "could not parse forwarding header"
+);
+
+impl std::error::Error for ClientIpResolveErr {}
+
+/// How trustworthiness of a proxy chain is determined.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ClientIpSource<'a> {
+    /// Pop hops from the right of the chain as long as each is contained in this set of trusted
+    /// IPs, per [`TrustedIps::contains()`].
+    TrustedSet(&'a TrustedIps),
+
+    /// Trust exactly this many hops, counted from the right of the chain, regardless of their
+    /// address. Useful when the number of intermediate proxies is fixed and known but their IPs
+    /// are not (e.g. an autoscaled load balancer fleet).
+    TrustedHopCount(usize),
+}
+
+/// Resolves the real client IP from a connection peer address and a comma-separated
+/// `X-Forwarded-For` header value.
+///
+/// The list is rightmost-closest: the rightmost entry is the hop nearest to this server. The
+/// connection peer is checked first, then hops are popped from the right for as long as they're
+/// trusted, and the first untrusted address encountered is returned as the real client IP. If
+/// the peer and every hop in the header are trusted, [`ClientIpResolveErr::ChainFullyTrusted`]
+/// is returned.
+pub fn resolve_from_x_forwarded_for(
+    peer_addr: IpAddr,
+    x_forwarded_for: &str,
+    source: ClientIpSource<'_>,
+) -> Result<IpAddr, ClientIpResolveErr> {
+    let mut chain = x_forwarded_for
+        .split(',')
+        .map(str::trim)
+        .filter(|hop| !hop.is_empty())
+        .map(|hop| hop.parse::<IpAddr>().map_err(|_| ClientIpResolveErr::Header))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    chain.push(peer_addr);
+
+    resolve_chain(&chain, source)
+}
+
+/// Resolves the real client IP from a connection peer address and a `Forwarded` header value
+/// (RFC 7239), as an alternative to [`resolve_from_x_forwarded_for()`].
+///
+/// Hops are read in header order (leftmost = original client, rightmost = most recently added
+/// hop, same convention as `X-Forwarded-For`) and popped from the right for as long as they're
+/// trusted. A hop that is [`ForwardedFor::Obfuscated`] or [`ForwardedFor::Unknown`] can't be
+/// evaluated for trust, so one anywhere in the chain fails resolution with
+/// [`ClientIpResolveErr::Header`] rather than silently skipping it.
+pub fn resolve_from_forwarded(
+    peer_addr: IpAddr,
+    forwarded: &str,
+    source: ClientIpSource<'_>,
+) -> Result<IpAddr, ClientIpResolveErr> {
+    let elements = parse_forwarded_for(forwarded)?;
+
+    let mut chain = Vec::with_capacity(elements.len() + 1);
+
+    for element in elements {
+        match element {
+            ForwardedFor::Ip(ip) => chain.push(ip),
+            ForwardedFor::Obfuscated(_) | ForwardedFor::Unknown => {
+                return Err(ClientIpResolveErr::Header)
+            }
+        }
+    }
+
+    chain.push(peer_addr);
+
+    resolve_chain(&chain, source)
+}
+
+/// Resolves the real client IP from a pre-parsed hop chain, ordered closest-hop-last (i.e. the
+/// connection peer is the last element).
+fn resolve_chain(
+    chain: &[IpAddr],
+    source: ClientIpSource<'_>,
+) -> Result<IpAddr, ClientIpResolveErr> {
+    match source {
+        ClientIpSource::TrustedSet(trusted_ips) => chain
+            .iter()
+            .rev()
+            .find(|hop| !trusted_ips.contains(**hop))
+            .copied()
+            .ok_or(ClientIpResolveErr::ChainFullyTrusted),
+
+        ClientIpSource::TrustedHopCount(trusted_hops) => chain
+            .len()
+            .checked_sub(trusted_hops + 1)
+            .and_then(|idx| chain.get(idx))
+            .copied()
+            .ok_or(ClientIpResolveErr::ChainFullyTrusted),
+    }
+}
+
+/// A single `for=` element of a `Forwarded` header, per [RFC 7239].
+///
+/// [RFC 7239]: https://datatracker.ietf.org/doc/html/rfc7239
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardedFor {
+    /// A resolvable IP address, optionally with a port that was discarded.
+    Ip(IpAddr),
+
+    /// An obfuscated identifier (e.g. `_hidden`), which cannot be resolved to an address.
+    Obfuscated(String),
+
+    /// The literal `unknown` value, meaning the proxy does not know the address of the hop.
+    Unknown,
+}
+
+/// Parses the `for=` parameters of a `Forwarded` header (RFC 7239), in header order (leftmost =
+/// original client, rightmost = most recently added hop, same convention as
+/// `X-Forwarded-For`).
+///
+/// Handles quoted values, `:port` suffixes, and the bracketed (`"[::1]:8080"`) syntax used for
+/// IPv6 addresses. Parameter names are matched case-insensitively, per RFC 7239.
+pub fn parse_forwarded_for(header: &str) -> Result<Vec<ForwardedFor>, ClientIpResolveErr> {
+    header
+        .split(',')
+        .map(|element| {
+            element
+                .split(';')
+                .map(str::trim)
+                .find_map(strip_for_prefix)
+                .ok_or(ClientIpResolveErr::Header)
+                .and_then(parse_for_value)
+        })
+        .collect()
+}
+
+/// Strips a case-insensitive `for=` prefix from a `Forwarded` header parameter.
+fn strip_for_prefix(param: &str) -> Option<&str> {
+    let prefix = param.get(.."for=".len())?;
+    prefix.eq_ignore_ascii_case("for=").then(|| &param["for=".len()..])
+}
+
+fn parse_for_value(value: &str) -> Result<ForwardedFor, ClientIpResolveErr> {
+    let value = value.trim().trim_matches('"');
+
+    if value.eq_ignore_ascii_case("unknown") {
+        return Ok(ForwardedFor::Unknown);
+    }
+
+    if let Some(obfuscated) = value.strip_prefix('_') {
+        return Ok(ForwardedFor::Obfuscated(format!("_{obfuscated}")));
+    }
+
+    // bracketed IPv6, optionally with a port: `[::1]` or `[::1]:8080`
+    if let Some(rest) = value.strip_prefix('[') {
+        let addr = rest.split(']').next().ok_or(ClientIpResolveErr::Header)?;
+        return addr
+            .parse()
+            .map(ForwardedFor::Ip)
+            .map_err(|_| ClientIpResolveErr::Header);
+    }
+
+    // bare address, or address:port for IPv4
+    let addr = value.split(':').next().ok_or(ClientIpResolveErr::Header)?;
+
+    addr.parse()
+        .map(ForwardedFor::Ip)
+        .map_err(|_| ClientIpResolveErr::Header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_through_trusted_set() {
+        let trusted = TrustedIps::new().add_ip_range(
+            "10.0.0.0/8"
+                .parse::<cidr_utils::cidr::IpCidr>()
+                .unwrap(),
+        );
+
+        let peer = "10.0.0.1".parse().unwrap();
+        let xff = "203.0.113.5, 10.0.0.2";
+
+        let resolved =
+            resolve_from_x_forwarded_for(peer, xff, ClientIpSource::TrustedSet(&trusted)).unwrap();
+        assert_eq!(resolved, "203.0.113.5".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn errs_when_chain_fully_trusted() {
+        let trusted = TrustedIps::new().add_ip_range(
+            "10.0.0.0/8"
+                .parse::<cidr_utils::cidr::IpCidr>()
+                .unwrap(),
+        );
+
+        let peer = "10.0.0.1".parse().unwrap();
+        let xff = "10.0.0.3, 10.0.0.2";
+
+        assert!(matches!(
+            resolve_from_x_forwarded_for(peer, xff, ClientIpSource::TrustedSet(&trusted)),
+            Err(ClientIpResolveErr::ChainFullyTrusted)
+        ));
+    }
+
+    #[test]
+    fn resolves_through_trusted_hop_count() {
+        let peer = "10.0.0.1".parse().unwrap();
+        let xff = "203.0.113.5, 10.0.0.2";
+
+        let resolved =
+            resolve_from_x_forwarded_for(peer, xff, ClientIpSource::TrustedHopCount(2)).unwrap();
+        assert_eq!(resolved, "203.0.113.5".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn parses_forwarded_header() {
+        let parsed = parse_forwarded_for(
+            r#"for=192.0.2.60;proto=http;by=203.0.113.43, for="[2001:db8:cafe::17]:4711""#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed,
+            vec![
+                ForwardedFor::Ip("192.0.2.60".parse().unwrap()),
+                ForwardedFor::Ip("2001:db8:cafe::17".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_forwarded_header_case_insensitive_param_name() {
+        let parsed = parse_forwarded_for(r#"For=192.0.2.60, FOR="[2001:db8::1]""#).unwrap();
+
+        assert_eq!(
+            parsed,
+            vec![
+                ForwardedFor::Ip("192.0.2.60".parse().unwrap()),
+                ForwardedFor::Ip("2001:db8::1".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_through_forwarded_header() {
+        let trusted = TrustedIps::new().add_ip_range(
+            "10.0.0.0/8"
+                .parse::<cidr_utils::cidr::IpCidr>()
+                .unwrap(),
+        );
+
+        let peer = "10.0.0.1".parse().unwrap();
+        let forwarded = "for=203.0.113.5, for=10.0.0.2";
+
+        let resolved =
+            resolve_from_forwarded(peer, forwarded, ClientIpSource::TrustedSet(&trusted)).unwrap();
+        assert_eq!(resolved, "203.0.113.5".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn resolving_forwarded_header_rejects_non_ip_hops() {
+        let trusted = TrustedIps::new();
+        let peer = "10.0.0.1".parse().unwrap();
+        let forwarded = "for=_hidden, for=10.0.0.2";
+
+        assert!(matches!(
+            resolve_from_forwarded(peer, forwarded, ClientIpSource::TrustedSet(&trusted)),
+            Err(ClientIpResolveErr::Header)
+        ));
+    }
+
+    #[test]
+    fn parses_obfuscated_and_unknown_forwarded_for() {
+        let parsed = parse_forwarded_for("for=_hidden, for=unknown").unwrap();
+
+        assert_eq!(
+            parsed,
+            vec![
+                ForwardedFor::Obfuscated("_hidden".to_owned()),
+                ForwardedFor::Unknown,
+            ]
+        );
+    }
+}